@@ -2,7 +2,10 @@
 extern crate clap;
 
 use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
-use csv::{Reader, ReaderBuilder, Writer, WriterBuilder, ByteRecordsIter, ByteRecord};
+use csv::{Reader, ReaderBuilder, Terminator, Writer, WriterBuilder, ByteRecordsIter, ByteRecord};
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
 use std::{env, process};
 use std::error::Error;
 use std::fs::File;
@@ -10,56 +13,198 @@ use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::collections::HashMap;
 use std::str::from_utf8;
 
-fn get_delimiter(matches: &ArgMatches) -> u8 {
-   return match matches.value_of("delimiter") {
-      Some(s) => {
-         if s.len() > 1 {
-            error_exit("Delimiter must be a single character")
-         }
-         s.as_bytes()[0]
+fn get_single_byte(matches: &ArgMatches, name: &str) -> Option<u8> {
+   return matches.value_of(name).map(|s| {
+      if s.len() != 1 {
+         error_exit(&format!("{} must be a single character", name).to_owned());
       }
-      None    => b','
-   };
+
+      s.as_bytes()[0]
+   });
 }
 
-struct CutConfig {
+struct CsvFormat {
    delimiter: u8,
+   gzip: bool,
+   quote: u8,
+   escape: Option<u8>,
+   terminator: Option<Terminator>,
+   no_quoting: bool
+}
+
+fn get_csv_format(matches: &ArgMatches) -> CsvFormat {
+   let delimiter = get_single_byte(matches, "delimiter").unwrap_or(b',');
+   let gzip = matches.is_present("gzip");
+   let quote = get_single_byte(matches, "quote").unwrap_or(b'"');
+   let escape = get_single_byte(matches, "escape");
+   let terminator = matches.value_of("terminator").map(|s| {
+      if s.eq_ignore_ascii_case("crlf") || s == "\r\n" {
+         Terminator::CRLF
+      } else if s.len() == 1 {
+         Terminator::Any(s.as_bytes()[0])
+      } else {
+         error_exit("Terminator must be a single character or 'CRLF'");
+         unreachable!()
+      }
+   });
+   let no_quoting = matches.is_present("no-quoting");
+
+   CsvFormat { delimiter, gzip, quote, escape, terminator, no_quoting }
+}
+
+struct CutConfig {
+   format: CsvFormat,
    columns: Option<Vec<usize>>,
+   fields: Option<Vec<String>>,
+   invert: bool,
    input_file: Option<String>,
    output_file: Option<String>
 }
 
 impl CutConfig {
    fn new(matches: &ArgMatches) -> CutConfig {
-      let delimiter = get_delimiter(matches);
+      let format = get_csv_format(matches);
+      let invert = matches.is_present("complement")
+         || matches.value_of("columns").or(matches.value_of("fields"))
+            .map_or(false, |s| s.starts_with('!'));
       let columns = matches.value_of("columns")
-         .map(|s| parse_columns(&s.to_string()));
+         .map(|s| parse_columns(&strip_invert(s)));
+      let fields = matches.value_of("fields")
+         .map(|s| strip_invert(s).split(',').map(|s| s.to_string()).collect::<Vec<_>>());
       let input_file = matches.value_of("input-file").map(|s| s.to_string());
       let output_file = matches.value_of("output-file").map(|s| s.to_string());
 
-      CutConfig { delimiter, columns, input_file, output_file }
+      CutConfig { format, columns, fields, invert, input_file, output_file }
    }
 }
 
 struct ReorderConfig {
-   delimiter: u8,
+   format: CsvFormat,
    columns: Option<Vec<usize>>,
    fields: Option<Vec<String>>,
+   invert: bool,
    input_file: Option<String>,
    output_file: Option<String>
 }
 
 impl ReorderConfig {
    fn new(matches: &ArgMatches) -> ReorderConfig {
-      let delimiter = get_delimiter(matches);
+      let format = get_csv_format(matches);
+      let invert = matches.value_of("columns").or(matches.value_of("fields"))
+         .map_or(false, |s| s.starts_with('!'));
       let columns = matches.value_of("columns")
-         .map(|s| parse_reorder(&s.to_string()));
+         .map(|s| parse_reorder(&strip_invert(s)));
+      let fields = matches.value_of("fields")
+         .map(|s| strip_invert(s).split(',').map(|s| s.to_string()).collect::<Vec<_>>());
+      let input_file = matches.value_of("input-file").map(|s| s.to_string());
+      let output_file = matches.value_of("output-file").map(|s| s.to_string());
+
+      ReorderConfig { format, columns, fields, invert, input_file, output_file }
+   }
+}
+
+struct JoinConfig {
+   format: CsvFormat,
+   columns1: Option<Vec<usize>>,
+   columns2: Option<Vec<usize>>,
+   fields1: Option<Vec<String>>,
+   fields2: Option<Vec<String>>,
+   left: bool,
+   right: bool,
+   full: bool,
+   cross: bool,
+   file1: String,
+   file2: String,
+   output_file: Option<String>
+}
+
+impl JoinConfig {
+   fn new(matches: &ArgMatches) -> JoinConfig {
+      let format = get_csv_format(matches);
+      let columns1 = matches.value_of("columns1").map(|s| parse_key_columns(&s.to_string()));
+      let columns2 = matches.value_of("columns2").map(|s| parse_key_columns(&s.to_string()));
+      let fields1 = matches.value_of("fields1")
+         .map(|s| s.split(',').map(|s| s.to_string()).collect::<Vec<_>>());
+      let fields2 = matches.value_of("fields2")
+         .map(|s| s.split(',').map(|s| s.to_string()).collect::<Vec<_>>());
+      let left = matches.is_present("left");
+      let right = matches.is_present("right");
+      let full = matches.is_present("full");
+      let cross = matches.is_present("cross");
+      let file1 = matches.value_of("file1").unwrap().to_string();
+      let file2 = matches.value_of("file2").unwrap().to_string();
+      let output_file = matches.value_of("output-file").map(|s| s.to_string());
+
+      if !cross && columns1.is_none() && fields1.is_none() {
+         error_exit("join requires --columns1 or --fields1 (or --cross)");
+      }
+      if !cross && columns2.is_none() && fields2.is_none() {
+         error_exit("join requires --columns2 or --fields2 (or --cross)");
+      }
+
+      JoinConfig { format, columns1, columns2, fields1, fields2,
+                   left, right, full, cross, file1, file2, output_file }
+   }
+}
+
+enum CatMode {
+   Rows,
+   Columns,
+   RowsKey
+}
+
+struct CatConfig {
+   format: CsvFormat,
+   mode: CatMode,
+   pad: bool,
+   input_files: Vec<String>,
+   output_file: Option<String>
+}
+
+impl CatConfig {
+   fn new(matches: &ArgMatches) -> CatConfig {
+      let format = get_csv_format(matches);
+      let mode = if matches.is_present("columns") {
+         CatMode::Columns
+      } else if matches.is_present("rowskey") {
+         CatMode::RowsKey
+      } else {
+         CatMode::Rows
+      };
+      let pad = matches.is_present("pad");
+      let input_files = matches.values_of("files")
+         .map(|values| values.map(|s| s.to_string()).collect())
+         .unwrap_or_else(Vec::new);
+      let output_file = matches.value_of("output-file").map(|s| s.to_string());
+
+      CatConfig { format, mode, pad, input_files, output_file }
+   }
+}
+
+struct FillConfig {
+   format: CsvFormat,
+   columns: Option<Vec<usize>>,
+   fields: Option<Vec<String>>,
+   default: Option<Vec<u8>>,
+   first: bool,
+   backfill: bool,
+   input_file: Option<String>,
+   output_file: Option<String>
+}
+
+impl FillConfig {
+   fn new(matches: &ArgMatches) -> FillConfig {
+      let format = get_csv_format(matches);
+      let columns = matches.value_of("columns").map(|s| parse_columns(&s.to_string()));
       let fields = matches.value_of("fields")
          .map(|s| s.split(',').map(|s| s.to_string()).collect::<Vec<_>>());
+      let default = matches.value_of("default").map(|s| s.as_bytes().to_vec());
+      let first = matches.is_present("first");
+      let backfill = matches.is_present("backfill");
       let input_file = matches.value_of("input-file").map(|s| s.to_string());
       let output_file = matches.value_of("output-file").map(|s| s.to_string());
 
-      ReorderConfig { delimiter, columns, fields, input_file, output_file }
+      FillConfig { format, columns, fields, default, first, backfill, input_file, output_file }
    }
 }
 
@@ -68,6 +213,25 @@ fn error_exit(error: &str) {
    process::exit(1);
 }
 
+fn is_broken_pipe(error: &Box<dyn Error>) -> bool {
+   if let Some(csv_err) = error.downcast_ref::<csv::Error>() {
+      return match csv_err.kind() {
+         csv::ErrorKind::Io(err) => err.kind() == io::ErrorKind::BrokenPipe,
+         _                       => false
+      };
+   }
+
+   return error.downcast_ref::<io::Error>()
+      .map_or(false, |err| err.kind() == io::ErrorKind::BrokenPipe);
+}
+
+fn strip_invert(selector: &str) -> String {
+   return match selector.strip_prefix('!') {
+      Some(rest) => rest.to_string(),
+      None       => selector.to_string()
+   };
+}
+
 fn parse_number(str: &String) -> usize {
    return str.parse::<usize>()
       .expect(format!("Invalid number: '{}'", str).as_str());
@@ -122,24 +286,186 @@ fn parse_reorder(columns_str: &String) -> Vec<usize> {
    return columns;
 }
 
-fn fields_to_columns(iter: &mut ByteRecordsIter<Box<dyn BufRead>>, 
-                     writer: &mut Writer<Box<dyn Write>>,
-                     fields: &Vec<String>) -> Result<Vec<usize>, Box<dyn Error>> {
-   let mut columns: Vec<usize> = Vec::new();
+fn parse_key_columns(columns_str: &String) -> Vec<usize> {
+   let mut columns : Vec<usize> = Vec::new();
+
+   for column_str in columns_str.split(',').collect::<Vec<_>>().iter() {
+      let column = parse_number(&column_str.to_string());
+
+      if column < 1 {
+         error_exit(&format!("Invalid column: {}", column).to_owned());
+      }
+
+      columns.push(column - 1);
+   }
+
+   return columns;
+}
+
+fn resolve_key_fields(header: &ByteRecord, fields: &Vec<String>) -> Vec<usize> {
    let mut map = HashMap::new();
-   let mut out_record: ByteRecord = ByteRecord::new();
-   let in_record = iter.next().expect("No header line found")?;
 
-   (0..in_record.len()).for_each(|i| {
-      map.insert(from_utf8(&in_record[i]).unwrap().to_string(), i);
+   (0..header.len()).for_each(|i| {
+      map.insert(from_utf8(&header[i]).unwrap().to_string(), i);
+   });
+
+   return fields.iter()
+      .map(|field| match map.get(field) {
+         Some(&i) => i,
+         None     => { error_exit(&format!("Column '{}' not found in header", field)); 0 }
+      })
+      .collect();
+}
+
+fn join_key(record: &ByteRecord, columns: &Vec<usize>) -> Vec<u8> {
+   let mut key: Vec<u8> = Vec::new();
+
+   for &column in columns {
+      key.extend_from_slice(record.get(column).unwrap_or(&[]));
+      key.push(0x1f);
+   }
+
+   return key;
+}
+
+fn write_joined(writer: &mut Writer<Box<dyn Write>>, out_record: &mut ByteRecord,
+                left: Option<&ByteRecord>, left_width: usize,
+                right: Option<&ByteRecord>, right_width: usize) -> Result<(), Box<dyn Error>> {
+   out_record.clear();
+
+   match left {
+      Some(record) => record.iter().for_each(|field| out_record.push_field(field)),
+      None         => (0..left_width).for_each(|_| out_record.push_field(b""))
+   }
+
+   match right {
+      Some(record) => record.iter().for_each(|field| out_record.push_field(field)),
+      None         => (0..right_width).for_each(|_| out_record.push_field(b""))
+   }
+
+   writer.write_record(&*out_record)?;
+
+   return Ok(());
+}
+
+fn resolve_fields(header: &ByteRecord, fields: &Vec<String>) -> Vec<usize> {
+   let mut selected: Vec<usize> = Vec::new();
+   let mut map = HashMap::new();
+
+   (0..header.len()).for_each(|i| {
+      map.insert(from_utf8(&header[i]).unwrap().to_string(), i);
    });
 
    for field in fields {
-      let column = *map.get(field)
-         .expect(&format!("Column '{}' not found in header", field)
-                 .to_owned()) as usize;
-      columns.push(column);
-      out_record.push_field(field.as_bytes());
+      if let Some(&column) = map.get(field) {
+         selected.push(column);
+      } else if field.contains('-') {
+         let range: Vec<&str> = field.splitn(2, '-').collect();
+         let start = match map.get(range[0]) {
+            Some(&i) => i,
+            None     => { error_exit(&format!("Column '{}' not found in header", range[0])); 0 }
+         };
+         let end = match map.get(range[1]) {
+            Some(&i) => i,
+            None     => { error_exit(&format!("Column '{}' not found in header", range[1])); 0 }
+         };
+
+         if start <= end {
+            (start..=end).for_each(|i| selected.push(i));
+         } else {
+            (end..=start).rev().for_each(|i| selected.push(i));
+         }
+      } else {
+         error_exit(&format!("Column '{}' not found in header", field));
+      }
+   }
+
+   return selected;
+}
+
+fn build_filled_record(record: &ByteRecord, last_values: &mut HashMap<usize, Vec<u8>>,
+                       target_columns: &Vec<usize>, first: bool) -> ByteRecord {
+   let mut out_record = ByteRecord::new();
+
+   for idx in 0..record.len() {
+      let field = &record[idx];
+      let is_target = target_columns.contains(&idx);
+
+      if is_target && field.is_empty() {
+         match last_values.get(&idx) {
+            Some(value) => out_record.push_field(value),
+            None        => out_record.push_field(b"")
+         }
+      } else {
+         out_record.push_field(field);
+
+         if is_target && !field.is_empty() {
+            if first {
+               last_values.entry(idx).or_insert_with(|| field.to_vec());
+            } else {
+               last_values.insert(idx, field.to_vec());
+            }
+         }
+      }
+   }
+
+   return out_record;
+}
+
+fn flush_resolved(pending: &mut Vec<ByteRecord>, last_values: &HashMap<usize, Vec<u8>>,
+                  target_columns: &Vec<usize>,
+                  writer: &mut Writer<Box<dyn Write>>) -> Result<(), Box<dyn Error>> {
+   let mut remaining = Vec::new();
+
+   for record in pending.drain(..) {
+      let mut resolved = ByteRecord::new();
+      let mut complete = true;
+
+      for idx in 0..record.len() {
+         let field = &record[idx];
+
+         if target_columns.contains(&idx) && field.is_empty() {
+            match last_values.get(&idx) {
+               Some(value) => resolved.push_field(value),
+               None        => {
+                  resolved.push_field(b"");
+                  complete = false;
+               }
+            }
+         } else {
+            resolved.push_field(field);
+         }
+      }
+
+      if complete {
+         writer.write_record(&resolved)?;
+      } else {
+         remaining.push(resolved);
+      }
+   }
+
+   *pending = remaining;
+
+   return Ok(());
+}
+
+fn fields_to_columns(iter: &mut ByteRecordsIter<Box<dyn BufRead>>,
+                     writer: &mut Writer<Box<dyn Write>>,
+                     fields: &Vec<String>,
+                     invert: bool) -> Result<Vec<usize>, Box<dyn Error>> {
+   let in_record = iter.next().expect("No header line found")?;
+   let selected = resolve_fields(&in_record, fields);
+
+   let columns: Vec<usize> = if invert {
+      (0..in_record.len()).filter(|i| !selected.contains(i)).collect()
+   } else {
+      selected
+   };
+
+   let mut out_record: ByteRecord = ByteRecord::new();
+
+   for &column in &columns {
+      out_record.push_field(&in_record[column]);
    }
 
    writer.write_record(&out_record)?;
@@ -147,52 +473,119 @@ fn fields_to_columns(iter: &mut ByteRecordsIter<Box<dyn BufRead>>,
    return Ok(columns)
 }
 
-fn get_reader(input_file: &Option<String>, delimiter: u8) 
+fn get_reader(input_file: &Option<String>, format: &CsvFormat)
    -> Result<Reader<Box<dyn BufRead>>, Box<dyn Error>> {
+   let is_gzip = format.gzip || input_file.as_ref().map_or(false, |f| f.ends_with(".gz"));
    let input: Box<dyn BufRead> = match input_file {
       Some(f) => Box::new(BufReader::new(File::open(f)?)),
       None    => Box::new(BufReader::new(io::stdin()))
    };
+   let input: Box<dyn BufRead> = if is_gzip {
+      Box::new(BufReader::new(MultiGzDecoder::new(input)))
+   } else {
+      input
+   };
+   let mut builder = ReaderBuilder::new();
+
+   builder.has_headers(false)
+      .delimiter(format.delimiter)
+      .quote(format.quote)
+      .escape(format.escape)
+      .quoting(!format.no_quoting);
+
+   if let Some(terminator) = format.terminator {
+      builder.terminator(terminator);
+   }
+
+   return Ok(builder.from_reader(input));
+}
 
-   return Ok(ReaderBuilder::new()
-      .has_headers(false)
-      .delimiter(delimiter)
-      .from_reader(input));
+fn get_readers(input_files: &Vec<String>, format: &CsvFormat)
+   -> Result<Vec<Reader<Box<dyn BufRead>>>, Box<dyn Error>> {
+   if input_files.is_empty() {
+      return Ok(vec![get_reader(&None, format)?]);
+   }
+
+   return input_files.iter()
+      .map(|f| get_reader(&Some(f.clone()), format))
+      .collect();
 }
 
-fn get_writer(output_file: &Option<String>, delimiter: u8) 
+fn get_writer(output_file: &Option<String>, format: &CsvFormat)
    -> Result<Writer<Box<dyn Write>>, Box<dyn Error>> {
+   let is_gzip = format.gzip || output_file.as_ref().map_or(false, |f| f.ends_with(".gz"));
    let output: Box<dyn Write> = match output_file {
       Some(f) => Box::new(BufWriter::new(File::create(f)?)),
       None    => Box::new(BufWriter::new(io::stdout()))
    };
-   
-   return Ok(WriterBuilder::new()
-      .delimiter(delimiter)
+   let output: Box<dyn Write> = if is_gzip {
+      Box::new(GzEncoder::new(output, Compression::default()))
+   } else {
+      output
+   };
+   let mut builder = WriterBuilder::new();
+
+   builder.delimiter(format.delimiter)
+      .quote(format.quote);
+
+   if format.no_quoting {
+      builder.quote_style(csv::QuoteStyle::Never);
+   }
+
+   if let Some(escape) = format.escape {
+      builder.escape(escape)
+         .double_quote(false);
+   }
+
+   if let Some(terminator) = format.terminator {
+      builder.terminator(terminator);
+   }
+
+   return Ok(builder
       .from_writer(output));
 }
 
 fn cut(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
    let config = CutConfig::new(&matches);
-   let columns = config.columns.unwrap();
-   let mut reader: Reader<Box<dyn BufRead>> = 
-      get_reader(&config.input_file, config.delimiter)?;
-   let mut writer: Writer<Box<dyn Write>> = 
-      get_writer(&config.output_file, config.delimiter)?;
+   let mut reader: Reader<Box<dyn BufRead>> =
+      get_reader(&config.input_file, &config.format)?;
+   let mut writer: Writer<Box<dyn Write>> =
+      get_writer(&config.output_file, &config.format)?;
    let mut out_record = ByteRecord::new();
+   let mut iter = reader.byte_records();
 
-   for result in reader.byte_records() {
-      let record = result?;
+   if config.fields.is_some() {
+      let fields = config.fields.unwrap();
+      let columns = fields_to_columns(&mut iter, &mut writer, &fields, config.invert)?;
 
-      for idx in 0..record.len() {
-         if idx < columns.len() && columns[idx] == 1 {
-            out_record.push_field(&record.get(idx).unwrap());
+      for result in iter {
+         let record = result?;
+
+         for &column in &columns {
+            out_record.push_field(&record.get(column).unwrap());
          }
+
+         writer.write_record(&out_record)?;
+         out_record.clear();
       }
+   } else {
+      let columns = config.columns.unwrap();
 
-      writer.write_record(&out_record)?;
-      out_record.clear();
-   }      
+      for result in iter {
+         let record = result?;
+
+         for idx in 0..record.len() {
+            let selected = idx < columns.len() && columns[idx] == 1;
+
+            if selected != config.invert {
+               out_record.push_field(&record.get(idx).unwrap());
+            }
+         }
+
+         writer.write_record(&out_record)?;
+         out_record.clear();
+      }
+   }
 
    writer.flush()?;
 
@@ -202,37 +595,365 @@ fn cut(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
 
 fn reorder(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
    let config = ReorderConfig::new(&matches);
-   let mut reader: Reader<Box<dyn BufRead>> = 
-      get_reader(&config.input_file, config.delimiter)?;
-   let mut writer: Writer<Box<dyn Write>> = 
-      get_writer(&config.output_file, config.delimiter)?;
+   let mut reader: Reader<Box<dyn BufRead>> =
+      get_reader(&config.input_file, &config.format)?;
+   let mut writer: Writer<Box<dyn Write>> =
+      get_writer(&config.output_file, &config.format)?;
    let mut out_record = ByteRecord::new();
    let mut iter = reader.byte_records();
    let columns;
 
+   let mut first_record: Option<ByteRecord> = None;
+
    if config.fields.is_some() {
       let fields = config.fields.unwrap();
-      columns = fields_to_columns(&mut iter, &mut writer, &fields)?;
+      columns = fields_to_columns(&mut iter, &mut writer, &fields, config.invert)?;
+   } else if config.invert {
+      let requested = config.columns.unwrap();
+      let record = iter.next().expect("No header line found")?;
+
+      columns = (0..record.len()).filter(|i| !requested.contains(&(i + 1))).collect();
+      first_record = Some(record);
    } else {
-      columns = config.columns.unwrap();
+      columns = config.columns.unwrap().into_iter().map(|c| c - 1).collect();
    }
 
-   for result in iter {
-      let record = result?;
-
+   let write_row = |record: &ByteRecord, out_record: &mut ByteRecord| -> Result<(), Box<dyn Error>> {
       for idx in 0..columns.len() {
          let column_idx = columns[idx] as usize;
 
-         if column_idx > record.len() {
-            error_exit(&format!("Invalid column: {}", column_idx).to_owned());
+         if column_idx >= record.len() {
+            error_exit(&format!("Invalid column: {}", column_idx + 1).to_owned());
          }
 
          out_record.push_field(&record.get(column_idx).unwrap());
       }
 
+      Ok(())
+   };
+
+   if let Some(record) = first_record {
+      write_row(&record, &mut out_record)?;
       writer.write_record(&out_record)?;
       out_record.clear();
-   }      
+   }
+
+   for result in iter {
+      let record = result?;
+
+      write_row(&record, &mut out_record)?;
+      writer.write_record(&out_record)?;
+      out_record.clear();
+   }
+
+   writer.flush()?;
+
+   Ok(())
+}
+
+fn join(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+   let config = JoinConfig::new(&matches);
+   let cross = config.cross;
+   let do_left = config.left;
+   let do_right = config.right;
+   let do_full = config.full;
+   let mut reader1: Reader<Box<dyn BufRead>> =
+      get_reader(&Some(config.file1), &config.format)?;
+   let mut reader2: Reader<Box<dyn BufRead>> =
+      get_reader(&Some(config.file2), &config.format)?;
+   let mut writer: Writer<Box<dyn Write>> =
+      get_writer(&config.output_file, &config.format)?;
+   let mut iter1 = reader1.byte_records();
+   let mut iter2 = reader2.byte_records();
+
+   let header1 = iter1.next().expect("No header line found in first file")?;
+   let header2 = iter2.next().expect("No header line found in second file")?;
+
+   let keys1 = match &config.fields1 {
+      Some(fields) => resolve_key_fields(&header1, fields),
+      None         => config.columns1.clone().unwrap_or_else(Vec::new)
+   };
+   let keys2 = match &config.fields2 {
+      Some(fields) => resolve_key_fields(&header2, fields),
+      None         => config.columns2.clone().unwrap_or_else(Vec::new)
+   };
+
+   let right_records: Vec<ByteRecord> = iter2.collect::<Result<_, _>>()?;
+   let mut right_map: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+
+   if !cross {
+      for (i, record) in right_records.iter().enumerate() {
+         right_map.entry(join_key(record, &keys2)).or_insert_with(Vec::new).push(i);
+      }
+   }
+
+   let right_width = header2.len();
+   let mut left_width = header1.len();
+
+   let mut header_record = ByteRecord::new();
+
+   header1.iter().for_each(|field| header_record.push_field(field));
+   header2.iter().for_each(|field| header_record.push_field(field));
+
+   writer.write_record(&header_record)?;
+
+   let mut matched2: std::collections::HashSet<usize> = std::collections::HashSet::new();
+   let mut out_record = ByteRecord::new();
+
+   let mut join_row = |record: ByteRecord, left_width: &mut usize,
+                       out_record: &mut ByteRecord, writer: &mut Writer<Box<dyn Write>>,
+                       matched2: &mut std::collections::HashSet<usize>|
+                       -> Result<(), Box<dyn Error>> {
+      if *left_width == 0 {
+         *left_width = record.len();
+      }
+
+      if cross {
+         for right in &right_records {
+            write_joined(writer, out_record, Some(&record), *left_width,
+                         Some(right), right_width)?;
+         }
+
+         return Ok(());
+      }
+
+      match right_map.get(&join_key(&record, &keys1)) {
+         Some(indices) => {
+            for &i in indices {
+               write_joined(writer, out_record, Some(&record), *left_width,
+                            Some(&right_records[i]), right_width)?;
+               matched2.insert(i);
+            }
+         }
+         None => {
+            if do_left || do_full {
+               write_joined(writer, out_record, Some(&record), *left_width,
+                            None, right_width)?;
+            }
+         }
+      }
+
+      Ok(())
+   };
+
+   for result in iter1 {
+      let record = result?;
+
+      join_row(record, &mut left_width, &mut out_record, &mut writer, &mut matched2)?;
+   }
+
+   if do_right || do_full {
+      for (i, record) in right_records.iter().enumerate() {
+         if !matched2.contains(&i) {
+            write_joined(&mut writer, &mut out_record, None, left_width,
+                         Some(record), right_width)?;
+         }
+      }
+   }
+
+   writer.flush()?;
+
+   Ok(())
+}
+
+fn cat_rows(config: &CatConfig, writer: &mut Writer<Box<dyn Write>>) -> Result<(), Box<dyn Error>> {
+   let mut readers = get_readers(&config.input_files, &config.format)?;
+
+   for (i, reader) in readers.iter_mut().enumerate() {
+      for (j, result) in reader.byte_records().enumerate() {
+         let record = result?;
+
+         if i > 0 && j == 0 {
+            continue;
+         }
+
+         writer.write_record(&record)?;
+      }
+   }
+
+   writer.flush()?;
+
+   Ok(())
+}
+
+fn cat_columns(config: &CatConfig, writer: &mut Writer<Box<dyn Write>>) -> Result<(), Box<dyn Error>> {
+   let mut readers = get_readers(&config.input_files, &config.format)?;
+   let mut widths = vec![0usize; readers.len()];
+   let mut out_record = ByteRecord::new();
+
+   loop {
+      out_record.clear();
+
+      let mut any = false;
+      let mut exhausted = false;
+
+      for (i, reader) in readers.iter_mut().enumerate() {
+         match reader.byte_records().next() {
+            Some(result) => {
+               let record = result?;
+
+               widths[i] = record.len();
+               any = true;
+               record.iter().for_each(|field| out_record.push_field(field));
+            }
+            None => {
+               exhausted = true;
+
+               if config.pad {
+                  (0..widths[i]).for_each(|_| out_record.push_field(b""));
+               }
+            }
+         }
+      }
+
+      if !any || (exhausted && !config.pad) {
+         break;
+      }
+
+      writer.write_record(&out_record)?;
+   }
+
+   writer.flush()?;
+
+   Ok(())
+}
+
+fn cat_rowskey(config: &CatConfig, writer: &mut Writer<Box<dyn Write>>) -> Result<(), Box<dyn Error>> {
+   let mut readers = get_readers(&config.input_files, &config.format)?;
+   let mut headers: Vec<ByteRecord> = Vec::new();
+
+   for reader in readers.iter_mut() {
+      headers.push(reader.byte_records().next().expect("No header line found")?);
+   }
+
+   let mut union: Vec<String> = Vec::new();
+   let mut seen: HashMap<String, ()> = HashMap::new();
+
+   for header in &headers {
+      for field in header.iter() {
+         let name = from_utf8(field).unwrap().to_string();
+
+         if !seen.contains_key(&name) {
+            seen.insert(name.clone(), ());
+            union.push(name);
+         }
+      }
+   }
+
+   let mut header_record = ByteRecord::new();
+   union.iter().for_each(|name| header_record.push_field(name.as_bytes()));
+   writer.write_record(&header_record)?;
+
+   let column_maps: Vec<HashMap<String, usize>> = headers.iter().map(|header| {
+      let mut map = HashMap::new();
+
+      (0..header.len()).for_each(|i| {
+         map.insert(from_utf8(&header[i]).unwrap().to_string(), i);
+      });
+
+      map
+   }).collect();
+
+   let mut out_record = ByteRecord::new();
+
+   for (i, reader) in readers.iter_mut().enumerate() {
+      for result in reader.byte_records() {
+         let record = result?;
+
+         out_record.clear();
+
+         for name in &union {
+            match column_maps[i].get(name) {
+               Some(&idx) => out_record.push_field(&record[idx]),
+               None       => out_record.push_field(b"")
+            }
+         }
+
+         writer.write_record(&out_record)?;
+      }
+   }
+
+   writer.flush()?;
+
+   Ok(())
+}
+
+fn cat(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+   let config = CatConfig::new(&matches);
+   let mut writer: Writer<Box<dyn Write>> = get_writer(&config.output_file, &config.format)?;
+
+   match config.mode {
+      CatMode::Rows    => cat_rows(&config, &mut writer),
+      CatMode::Columns => cat_columns(&config, &mut writer),
+      CatMode::RowsKey => cat_rowskey(&config, &mut writer)
+   }
+}
+
+fn fill(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+   let config = FillConfig::new(&matches);
+   let mut reader: Reader<Box<dyn BufRead>> =
+      get_reader(&config.input_file, &config.format)?;
+   let mut writer: Writer<Box<dyn Write>> =
+      get_writer(&config.output_file, &config.format)?;
+   let mut iter = reader.byte_records();
+
+   let target_columns: Vec<usize> = if let Some(fields) = &config.fields {
+      let header = iter.next().expect("No header line found")?;
+      let columns = resolve_fields(&header, fields);
+
+      writer.write_record(&header)?;
+
+      columns
+   } else {
+      let flags = config.columns.as_ref().unwrap();
+      let header = iter.next().expect("No header line found")?;
+
+      writer.write_record(&header)?;
+
+      (0..flags.len()).filter(|&i| flags[i] == 1).collect()
+   };
+
+   if let Some(default) = &config.default {
+      for result in iter {
+         let record = result?;
+         let mut out_record = ByteRecord::new();
+
+         for idx in 0..record.len() {
+            let field = &record[idx];
+
+            if target_columns.contains(&idx) && field.is_empty() {
+               out_record.push_field(default);
+            } else {
+               out_record.push_field(field);
+            }
+         }
+
+         writer.write_record(&out_record)?;
+      }
+   } else {
+      let mut last_values: HashMap<usize, Vec<u8>> = HashMap::new();
+      let mut pending: Vec<ByteRecord> = Vec::new();
+
+      for result in iter {
+         let record = result?;
+         let columns_before = last_values.len();
+         let out_record = build_filled_record(&record, &mut last_values, &target_columns, config.first);
+
+         if config.backfill {
+            pending.push(out_record);
+
+            if last_values.len() > columns_before {
+               flush_resolved(&mut pending, &last_values, &target_columns, &mut writer)?;
+            }
+         } else {
+            writer.write_record(&out_record)?;
+         }
+      }
+
+      for record in pending {
+         writer.write_record(&record)?;
+      }
+   }
 
    writer.flush()?;
 
@@ -246,20 +967,48 @@ fn parse_args(args: &Vec<String>) -> ArgMatches {
            .long("delimiter")
            .value_name("DELIMITER");
 
+   let arg_gzip = Arg::with_name("gzip")
+      .help("Treat input/output as gzip compressed, regardless of file extension")
+      .long("gzip");
+
+   let arg_quote = Arg::with_name("quote")
+      .help("Quote character if it's not a double quote")
+      .long("quote")
+      .value_name("QUOTE");
+
+   let arg_escape = Arg::with_name("escape")
+      .help("Escape character for quotes, for formats that escape instead of doubling")
+      .long("escape")
+      .value_name("ESCAPE");
+
+   let arg_terminator = Arg::with_name("terminator")
+      .help("Record terminator character, or 'CRLF'")
+      .long("terminator")
+      .value_name("TERMINATOR");
+
+   let arg_no_quoting = Arg::with_name("no-quoting")
+      .help("Disable quote processing entirely, treating quotes as literal data")
+      .long("no-quoting");
+
    let arg_columns = Arg::with_name("columns")
-      .help("A comma separated list of column indices")
+      .help("A comma separated list of column indices or ranges (e.g. 2,4-6), \
+             prefix with '!' to select the complement; reorder takes single \
+             indices only, and fill does not take the '!' prefix")
       .short("c")
       .long("columns")
-      .value_name("LIST")
-      .required(true)
-      .conflicts_with("fields");
+      .value_name("LIST");
 
    let arg_fields = Arg::with_name("fields")
-      .help("A comma separated list of column names")
+      .help("A comma separated list of column names or name ranges (e.g. name-age), \
+             prefix with '!' to select the complement; fill does not take the \
+             '!' prefix")
       .short("f")
       .long("fields")
-      .value_name("LIST")
-      .conflicts_with("columns");
+      .value_name("LIST");
+
+   let arg_complement = Arg::with_name("complement")
+      .help("Select the complement of the given columns/fields")
+      .long("complement");
 
    let arg_input = Arg::with_name("input-file")
       .help("CSV file")
@@ -273,6 +1022,88 @@ fn parse_args(args: &Vec<String>) -> ArgMatches {
       .long("output-file")
       .value_name("FILE");
 
+   let arg_columns1 = Arg::with_name("columns1")
+      .help("A comma separated list of key column indices in the first file")
+      .long("columns1")
+      .value_name("LIST");
+
+   let arg_columns2 = Arg::with_name("columns2")
+      .help("A comma separated list of key column indices in the second file")
+      .long("columns2")
+      .value_name("LIST");
+
+   let arg_fields1 = Arg::with_name("fields1")
+      .help("A comma separated list of key column names in the first file")
+      .long("fields1")
+      .value_name("LIST");
+
+   let arg_fields2 = Arg::with_name("fields2")
+      .help("A comma separated list of key column names in the second file")
+      .long("fields2")
+      .value_name("LIST");
+
+   let arg_left = Arg::with_name("left")
+      .help("Left outer join: keep unmatched rows from the first file")
+      .long("left");
+
+   let arg_right = Arg::with_name("right")
+      .help("Right outer join: keep unmatched rows from the second file")
+      .long("right");
+
+   let arg_full = Arg::with_name("full")
+      .help("Full outer join: keep unmatched rows from both files")
+      .long("full");
+
+   let arg_cross = Arg::with_name("cross")
+      .help("Cartesian product of both files, ignoring any key columns")
+      .long("cross");
+
+   let arg_file1 = Arg::with_name("file1")
+      .help("First CSV file")
+      .required(true)
+      .index(1);
+
+   let arg_file2 = Arg::with_name("file2")
+      .help("Second CSV file")
+      .required(true)
+      .index(2);
+
+   let arg_cat_columns = Arg::with_name("columns")
+      .help("Concatenate files side by side, in lockstep, one row from each")
+      .long("columns");
+
+   let arg_cat_rowskey = Arg::with_name("rowskey")
+      .help("Concatenate by rows, aligning on the union of all input headers")
+      .long("rowskey");
+
+   let arg_pad = Arg::with_name("pad")
+      .help("In columns mode, pad shorter inputs with empty fields instead of \
+             stopping at the shortest")
+      .long("pad");
+
+   let arg_files = Arg::with_name("files")
+      .help("CSV files to concatenate")
+      .multiple(true)
+      .required(true);
+
+   let arg_default = Arg::with_name("default")
+      .help("Fill every empty field in the selected columns with this constant, \
+             instead of forward-filling from prior rows")
+      .long("default")
+      .value_name("VALUE")
+      .conflicts_with("first")
+      .conflicts_with("backfill");
+
+   let arg_first = Arg::with_name("first")
+      .help("Fill with the first non-empty value seen in each column, \
+             instead of the most recent one")
+      .long("first");
+
+   let arg_backfill = Arg::with_name("backfill")
+      .help("Hold leading empty rows in a column until its first value appears, \
+             then fill them in retroactively")
+      .long("backfill");
+
    return App::new("CSV tool")
       .version(crate_version!())
       .setting(AppSettings::GlobalVersion)
@@ -280,14 +1111,87 @@ fn parse_args(args: &Vec<String>) -> ArgMatches {
       .subcommand(SubCommand::with_name("cut")
                   .about("Cuts out columns")
                   .arg(&arg_delimiter)
+                  .arg(&arg_gzip)
+                  .arg(&arg_quote)
+                  .arg(&arg_escape)
+                  .arg(&arg_terminator)
+                  .arg(&arg_no_quoting)
                   .arg(&arg_columns)
+                  .arg(&arg_fields)
+                  .arg(&arg_complement)
                   .arg(&arg_input)
-                  .arg(&arg_output))
+                  .arg(&arg_output)
+                  .group(ArgGroup::with_name("columns or fields")
+                         .args(&["columns", "fields"])
+                         .required(true)))
       .subcommand(SubCommand::with_name("reorder")
                   .about("Reorders columns")
                   .arg(&arg_delimiter)
+                  .arg(&arg_gzip)
+                  .arg(&arg_quote)
+                  .arg(&arg_escape)
+                  .arg(&arg_terminator)
+                  .arg(&arg_no_quoting)
+                  .arg(&arg_columns)
+                  .arg(&arg_fields)
+                  .arg(&arg_input)
+                  .arg(&arg_output)
+                  .group(ArgGroup::with_name("columns or fields")
+                         .args(&["columns", "fields"])
+                         .required(true)))
+      .subcommand(SubCommand::with_name("join")
+                  .about("Joins two CSV files on one or more key columns")
+                  .arg(&arg_delimiter)
+                  .arg(&arg_gzip)
+                  .arg(&arg_quote)
+                  .arg(&arg_escape)
+                  .arg(&arg_terminator)
+                  .arg(&arg_no_quoting)
+                  .arg(&arg_columns1)
+                  .arg(&arg_columns2)
+                  .arg(&arg_fields1)
+                  .arg(&arg_fields2)
+                  .arg(&arg_left)
+                  .arg(&arg_right)
+                  .arg(&arg_full)
+                  .arg(&arg_cross)
+                  .arg(&arg_file1)
+                  .arg(&arg_file2)
+                  .arg(&arg_output)
+                  .group(ArgGroup::with_name("join type")
+                         .args(&["left", "right", "full", "cross"]))
+                  .group(ArgGroup::with_name("columns1 or fields1")
+                         .args(&["columns1", "fields1"]))
+                  .group(ArgGroup::with_name("columns2 or fields2")
+                         .args(&["columns2", "fields2"])))
+      .subcommand(SubCommand::with_name("cat")
+                  .about("Concatenates CSV files by rows or by columns")
+                  .arg(&arg_delimiter)
+                  .arg(&arg_gzip)
+                  .arg(&arg_quote)
+                  .arg(&arg_escape)
+                  .arg(&arg_terminator)
+                  .arg(&arg_no_quoting)
+                  .arg(&arg_cat_columns)
+                  .arg(&arg_cat_rowskey)
+                  .arg(&arg_pad)
+                  .arg(&arg_files)
+                  .arg(&arg_output)
+                  .group(ArgGroup::with_name("cat mode")
+                         .args(&["columns", "rowskey"])))
+      .subcommand(SubCommand::with_name("fill")
+                  .about("Forward-fills empty fields in selected columns")
+                  .arg(&arg_delimiter)
+                  .arg(&arg_gzip)
+                  .arg(&arg_quote)
+                  .arg(&arg_escape)
+                  .arg(&arg_terminator)
+                  .arg(&arg_no_quoting)
                   .arg(&arg_columns)
                   .arg(&arg_fields)
+                  .arg(&arg_default)
+                  .arg(&arg_first)
+                  .arg(&arg_backfill)
                   .arg(&arg_input)
                   .arg(&arg_output)
                   .group(ArgGroup::with_name("columns or fields")
@@ -301,13 +1205,17 @@ fn main() {
    let args: Vec<String> = env::args().collect();
    let matches = parse_args(&args);
    let result = match matches.subcommand() {
-      ("cut",     Some(matches)) => cut( &matches), 
-      ("reorder", Some(matches)) => reorder(&matches), 
-      _                          => Ok(()), 
+      ("cut",     Some(matches)) => cut( &matches),
+      ("reorder", Some(matches)) => reorder(&matches),
+      ("join",    Some(matches)) => join(&matches),
+      ("cat",     Some(matches)) => cat(&matches),
+      ("fill",    Some(matches)) => fill(&matches),
+      _                          => Ok(()),
    };
 
    match result {
-      Err(err) => error_exit(&err.to_string()),
-      Ok(_)    => ()
+      Err(err) if is_broken_pipe(&err) => process::exit(0),
+      Err(err)                         => error_exit(&err.to_string()),
+      Ok(_)                            => ()
    }
 }